@@ -1,14 +1,19 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fs;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::io::{self, IsTerminal, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use tui::{
     backend::CrosstermBackend,
     Terminal,
     widgets::{Block, Borders, List, ListItem, Paragraph},
     layout::{Layout, Constraint, Direction},
-    style::{Modifier, Style},
+    style::{Color, Modifier, Style},
     text::{Span, Spans},
 };
 use crossterm::{
@@ -19,14 +24,545 @@ use crossterm::{
 use strsim::levenshtein;
 use signal_hook::consts::SIGINT;
 use signal_hook::flag;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-fn load_dictionary() -> Vec<String> {
-    fs::read_to_string("/usr/share/dict/words")
-        .map(|content| content.lines().map(String::from).collect())
-        .unwrap_or_else(|_| {
-            eprintln!("Error: Could not find or read the dictionary file at /usr/share/dict/words.");
-            std::process::exit(1);
-        })
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const PENALTY_GAP: i64 = 1;
+
+/// Keep only the best-scoring matches; dictionaries can have hundreds of
+/// thousands of entries and the UI only ever shows a screenful.
+const MAX_RESULTS: usize = 200;
+/// How long the query must sit still before a scan is dispatched, so a burst
+/// of keystrokes doesn't each trigger their own full dictionary scan.
+const DEBOUNCE: Duration = Duration::from_millis(30);
+/// How many dictionary entries the worker scores between staleness checks.
+const CANCEL_CHECK_INTERVAL: usize = 256;
+/// How long to wait for a key event before looping back to check for fresh
+/// worker results; keeps the UI thread idle instead of busy-polling.
+const INPUT_POLL_TIMEOUT: Duration = Duration::from_millis(30);
+
+/// How `query` is matched against the dictionary. Cycled with Ctrl-T.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MatchMode {
+    Prefix,
+    Substring,
+    Fuzzy,
+    EditDistance,
+}
+
+impl MatchMode {
+    fn next(self) -> Self {
+        match self {
+            MatchMode::Prefix => MatchMode::Substring,
+            MatchMode::Substring => MatchMode::Fuzzy,
+            MatchMode::Fuzzy => MatchMode::EditDistance,
+            MatchMode::EditDistance => MatchMode::Prefix,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            MatchMode::Prefix => "PREFIX",
+            MatchMode::Substring => "SUBSTRING",
+            MatchMode::Fuzzy => "FUZZY",
+            MatchMode::EditDistance => "EDIT",
+        }
+    }
+
+    fn as_config_str(self) -> &'static str {
+        match self {
+            MatchMode::Prefix => "prefix",
+            MatchMode::Substring => "substring",
+            MatchMode::Fuzzy => "fuzzy",
+            MatchMode::EditDistance => "edit_distance",
+        }
+    }
+
+    fn from_config_str(s: &str) -> Option<Self> {
+        match s {
+            "prefix" => Some(MatchMode::Prefix),
+            "substring" => Some(MatchMode::Substring),
+            "fuzzy" => Some(MatchMode::Fuzzy),
+            "edit_distance" => Some(MatchMode::EditDistance),
+            _ => None,
+        }
+    }
+}
+
+/// Where the last-used `MatchMode` is persisted between launches.
+fn config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("spelf").join("match_mode"))
+}
+
+fn load_match_mode() -> MatchMode {
+    config_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| MatchMode::from_config_str(contents.trim()))
+        .unwrap_or(MatchMode::Fuzzy)
+}
+
+fn save_match_mode(mode: MatchMode) {
+    let Some(path) = config_path() else { return };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let _ = fs::write(path, mode.as_config_str());
+}
+
+/// The query text plus a cursor position, both tracked in grapheme clusters
+/// so editing and rendering stay correct for multibyte and combining
+/// characters rather than just appending/popping at the tail.
+struct QueryState {
+    text: String,
+    /// Cursor position as a grapheme index into `text` (0..=len()).
+    cursor: usize,
+}
+
+impl QueryState {
+    fn new() -> Self {
+        QueryState { text: String::new(), cursor: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    fn graphemes(&self) -> Vec<&str> {
+        self.text.graphemes(true).collect()
+    }
+
+    fn len(&self) -> usize {
+        self.graphemes().len()
+    }
+
+    fn byte_offset(&self, grapheme_index: usize) -> usize {
+        self.graphemes().iter().take(grapheme_index).map(|g| g.len()).sum()
+    }
+
+    fn insert_char(&mut self, c: char) {
+        let offset = self.byte_offset(self.cursor);
+        self.text.insert(offset, c);
+        // Re-count graphemes up to the inserted char rather than assuming it
+        // started a new one: a combining mark merges into the grapheme
+        // cluster before it instead of advancing the cursor past `len()`.
+        let new_offset = offset + c.len_utf8();
+        self.cursor = self.text[..new_offset].graphemes(true).count();
+    }
+
+    /// Backspace: deletes the grapheme cluster before the cursor.
+    fn delete_before_cursor(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+        let graphemes = self.graphemes();
+        let start: usize = graphemes[..self.cursor - 1].iter().map(|g| g.len()).sum();
+        let end = start + graphemes[self.cursor - 1].len();
+        self.text.replace_range(start..end, "");
+        self.cursor -= 1;
+    }
+
+    /// Ctrl-W / Alt-Backspace: deletes the word before the cursor, including
+    /// any trailing whitespace.
+    fn delete_word_before_cursor(&mut self) {
+        if self.cursor == 0 {
+            return;
+        }
+        let graphemes = self.graphemes();
+        let mut start_index = self.cursor;
+        while start_index > 0 && graphemes[start_index - 1].chars().all(char::is_whitespace) {
+            start_index -= 1;
+        }
+        while start_index > 0 && !graphemes[start_index - 1].chars().all(char::is_whitespace) {
+            start_index -= 1;
+        }
+        let start: usize = graphemes[..start_index].iter().map(|g| g.len()).sum();
+        let end: usize = graphemes[..self.cursor].iter().map(|g| g.len()).sum();
+        self.text.replace_range(start..end, "");
+        self.cursor = start_index;
+    }
+
+    fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.len());
+    }
+
+    fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    fn move_end(&mut self) {
+        self.cursor = self.len();
+    }
+}
+
+/// A dictionary word along with how it scored against the current query.
+#[derive(Clone)]
+struct Match {
+    word: String,
+    score: i64,
+    /// Byte offsets into `word` of the characters that satisfied the query.
+    positions: Vec<usize>,
+    /// This word's position in the original, merged dictionary.
+    dict_index: usize,
+}
+
+/// True if the candidate character at `i` starts a "word" (follows a
+/// separator, or is an uppercase letter following a lowercase one, as in
+/// camelCase).
+fn is_word_boundary(chars: &[char], i: usize) -> bool {
+    if i == 0 {
+        return true;
+    }
+    let prev = chars[i - 1];
+    let cur = chars[i];
+    prev == '_' || prev == '-' || prev == ' ' || (prev.is_lowercase() && cur.is_uppercase())
+}
+
+/// fzf-style fuzzy subsequence match: checks whether every character of
+/// `query` (already lowercased) occurs in `candidate` (original case, so
+/// word-boundary and byte-offset math line up with what the caller renders),
+/// in order, and scores the best such alignment. Characters are compared
+/// case-insensitively. Returns the score and the byte indices of the matched
+/// characters (into `candidate` as given), or `None` if `query` isn't a
+/// subsequence of `candidate`.
+fn fuzzy_match(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let cand_chars: Vec<char> = candidate.chars().collect();
+    let cand_lower: Vec<char> = cand_chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let query_chars: Vec<char> = query.chars().collect();
+    let n = cand_chars.len();
+    let m = query_chars.len();
+    if m > n {
+        return None;
+    }
+
+    let neg_inf = i64::MIN / 2;
+    // dp[j][i]: best score aligning query[..=j] with candidate[..=i], given
+    // query[j] is matched at candidate position i.
+    let mut dp = vec![vec![neg_inf; n]; m];
+    let mut back = vec![vec![usize::MAX; n]; m];
+
+    for i in 0..n {
+        if cand_lower[i] != query_chars[0] {
+            continue;
+        }
+        let mut score = SCORE_MATCH - PENALTY_GAP * i as i64;
+        if is_word_boundary(&cand_chars, i) {
+            score += BONUS_BOUNDARY;
+        }
+        dp[0][i] = score;
+    }
+
+    for j in 1..m {
+        for i in 0..n {
+            if cand_lower[i] != query_chars[j] {
+                continue;
+            }
+            let mut best = neg_inf;
+            let mut best_prev = usize::MAX;
+            for (ip, &prev_score) in dp[j - 1][..i].iter().enumerate() {
+                if prev_score == neg_inf {
+                    continue;
+                }
+                let gap = i - ip - 1;
+                let mut candidate_score = prev_score + SCORE_MATCH;
+                if gap == 0 {
+                    candidate_score += BONUS_CONSECUTIVE;
+                } else {
+                    candidate_score -= PENALTY_GAP * gap as i64;
+                }
+                if candidate_score > best {
+                    best = candidate_score;
+                    best_prev = ip;
+                }
+            }
+            if best == neg_inf {
+                continue;
+            }
+            if is_word_boundary(&cand_chars, i) {
+                best += BONUS_BOUNDARY;
+            }
+            dp[j][i] = best;
+            back[j][i] = best_prev;
+        }
+    }
+
+    let (best_i, &best_score) = dp[m - 1]
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, score)| **score)?;
+    if best_score == neg_inf {
+        return None;
+    }
+
+    let mut char_indices = vec![0usize; m];
+    let mut i = best_i;
+    for j in (0..m).rev() {
+        char_indices[j] = i;
+        if j == 0 {
+            break;
+        }
+        i = back[j][i];
+    }
+
+    let byte_offsets: Vec<usize> = candidate.char_indices().map(|(b, _)| b).collect();
+    let positions = char_indices.iter().map(|&ci| byte_offsets[ci]).collect();
+    Some((best_score, positions))
+}
+
+/// Scores one candidate against `query_lower` under `mode`, or `None` if it
+/// isn't a match.
+fn score_word(word: &str, dict_index: usize, query_lower: &str, mode: MatchMode) -> Option<Match> {
+    let lower = word.to_lowercase();
+    match mode {
+        MatchMode::Prefix => lower.starts_with(query_lower).then(|| Match {
+            word: word.to_string(),
+            score: 0,
+            positions: (0..query_lower.len()).collect(),
+            dict_index,
+        }),
+        MatchMode::Substring => lower.find(query_lower).map(|start| Match {
+            word: word.to_string(),
+            score: -(start as i64),
+            positions: (start..start + query_lower.len()).collect(),
+            dict_index,
+        }),
+        MatchMode::Fuzzy => fuzzy_match(query_lower, word).map(|(score, positions)| Match {
+            word: word.to_string(),
+            score,
+            positions,
+            dict_index,
+        }),
+        MatchMode::EditDistance => Some(Match {
+            word: word.to_string(),
+            score: -(levenshtein(query_lower, &lower) as i64),
+            positions: Vec::new(),
+            dict_index,
+        }),
+    }
+}
+
+/// Scores `dict` against `query_lower` under `mode`, checking `is_cancelled`
+/// every [`CANCEL_CHECK_INTERVAL`] entries and bailing out with `None` (no
+/// partial result) as soon as it returns true, so a stale scan doesn't waste
+/// the rest of the dictionary once the query has moved on.
+fn score_dict(
+    dict: &[String],
+    query_lower: &str,
+    mode: MatchMode,
+    is_cancelled: impl Fn() -> bool,
+) -> Option<Vec<Match>> {
+    let mut scored = Vec::new();
+    for (i, word) in dict.iter().enumerate() {
+        if i % CANCEL_CHECK_INTERVAL == 0 && is_cancelled() {
+            return None;
+        }
+        if let Some(m) = score_word(word, i, query_lower, mode) {
+            scored.push(m);
+        }
+    }
+    if is_cancelled() {
+        return None;
+    }
+    Some(scored)
+}
+
+/// Bounds `matches` to the `n` highest-scoring entries using a min-heap,
+/// rather than sorting the whole (potentially huge) result set.
+fn cap_top_n(matches: Vec<Match>, n: usize) -> Vec<Match> {
+    if matches.len() <= n {
+        return matches;
+    }
+    let mut heap: BinaryHeap<Reverse<ScoredMatch>> = BinaryHeap::with_capacity(n + 1);
+    for m in matches {
+        heap.push(Reverse(ScoredMatch(m)));
+        if heap.len() > n {
+            heap.pop();
+        }
+    }
+    heap.into_iter().map(|Reverse(sm)| sm.0).collect()
+}
+
+/// Wraps a [`Match`] so it can be ordered by score alone for [`cap_top_n`].
+struct ScoredMatch(Match);
+
+impl PartialEq for ScoredMatch {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.score == other.0.score
+    }
+}
+impl Eq for ScoredMatch {}
+impl PartialOrd for ScoredMatch {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for ScoredMatch {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.score.cmp(&other.0.score)
+    }
+}
+
+/// Orders an already-capped set of matches for display.
+fn sort_matches(matches: &mut [Match], query_lower: &str, mode: MatchMode) {
+    match mode {
+        MatchMode::Prefix => matches.sort_by(|a, b| a.word.cmp(&b.word)),
+        MatchMode::Fuzzy => matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| levenshtein(query_lower, &a.word).cmp(&levenshtein(query_lower, &b.word)))
+        }),
+        MatchMode::Substring | MatchMode::EditDistance => {
+            matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.word.cmp(&b.word)))
+        }
+    }
+}
+
+/// A query to score against the dictionary, tagged with a generation so
+/// stale work can be recognized and abandoned.
+struct WorkerRequest {
+    generation: u64,
+    query: String,
+    mode: MatchMode,
+}
+
+/// The outcome of scoring a [`WorkerRequest`], tagged with the same generation.
+struct WorkerResult {
+    generation: u64,
+    matches: Vec<Match>,
+}
+
+/// Runs on a dedicated thread: scores each incoming query against `dict`,
+/// coalescing any requests that piled up while it was busy and abandoning a
+/// scan as soon as `latest_generation` shows the query has moved on.
+fn run_worker(
+    dict: Arc<Vec<String>>,
+    request_rx: mpsc::Receiver<WorkerRequest>,
+    result_tx: mpsc::Sender<WorkerResult>,
+    latest_generation: Arc<AtomicU64>,
+) {
+    while let Ok(mut request) = request_rx.recv() {
+        while let Ok(newer) = request_rx.try_recv() {
+            request = newer;
+        }
+        let generation = request.generation;
+        let query_lower = request.query.to_lowercase();
+        let is_cancelled = || latest_generation.load(Ordering::Relaxed) != generation;
+
+        let Some(scored) = score_dict(&dict, &query_lower, request.mode, is_cancelled) else {
+            continue;
+        };
+        let mut capped = cap_top_n(scored, MAX_RESULTS);
+        sort_matches(&mut capped, &query_lower, request.mode);
+        let _ = result_tx.send(WorkerResult { generation, matches: capped });
+    }
+}
+
+/// Used when no dictionary paths are given and stdin is a TTY, to preserve
+/// `spelf`'s original out-of-the-box behavior.
+const DEFAULT_DICTIONARY_PATH: &str = "/usr/share/dict/words";
+
+/// Why a dictionary source could not be loaded.
+#[derive(Debug)]
+enum DictError {
+    Io { source_name: String, source: io::Error },
+    Parse { source_name: String, line: usize, message: String },
+}
+
+impl std::fmt::Display for DictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DictError::Io { source_name, source } => write!(f, "{source_name}: {source}"),
+            DictError::Parse { source_name, line, message } => {
+                write!(f, "{source_name}:{line}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DictError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DictError::Io { source, .. } => Some(source),
+            DictError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Parses one source's contents using the flashcard-deck list format: one
+/// entry per line, blank lines and `#`-prefixed comments ignored, entries
+/// trimmed and de-duplicated against everything already collected.
+fn parse_dictionary_source(
+    content: &str,
+    source_name: &str,
+    words: &mut Vec<String>,
+    seen: &mut HashSet<String>,
+) -> Result<(), DictError> {
+    for (i, raw_line) in content.lines().enumerate() {
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if trimmed.contains('\0') {
+            return Err(DictError::Parse {
+                source_name: source_name.to_string(),
+                line: i + 1,
+                message: "entry contains a NUL byte".to_string(),
+            });
+        }
+        if seen.insert(trimmed.to_string()) {
+            words.push(trimmed.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Loads and merges candidate words from `paths`, falling back to
+/// [`DEFAULT_DICTIONARY_PATH`] when none are given and stdin is a TTY, and
+/// additionally reading stdin when it's piped in. Entries are de-duplicated
+/// across all sources in the order they're first seen.
+fn load_dictionary(paths: &[PathBuf]) -> Result<Vec<String>, DictError> {
+    let mut words = Vec::new();
+    let mut seen = HashSet::new();
+    let stdin_is_piped = !io::stdin().is_terminal();
+
+    if paths.is_empty() && !stdin_is_piped {
+        let default_path = PathBuf::from(DEFAULT_DICTIONARY_PATH);
+        let source_name = default_path.display().to_string();
+        let content = fs::read_to_string(&default_path)
+            .map_err(|source| DictError::Io { source_name: source_name.clone(), source })?;
+        parse_dictionary_source(&content, &source_name, &mut words, &mut seen)?;
+        return Ok(words);
+    }
+
+    for path in paths {
+        let source_name = path.display().to_string();
+        let content = fs::read_to_string(path)
+            .map_err(|source| DictError::Io { source_name: source_name.clone(), source })?;
+        parse_dictionary_source(&content, &source_name, &mut words, &mut seen)?;
+    }
+
+    if stdin_is_piped {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|source| DictError::Io { source_name: "<stdin>".to_string(), source })?;
+        parse_dictionary_source(&content, "<stdin>", &mut words, &mut seen)?;
+    }
+
+    Ok(words)
 }
 
 fn setup_signal_handling() -> Arc<AtomicBool> {
@@ -52,11 +588,26 @@ fn cleanup_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Re
     Ok(())
 }
 
+/// Splits `word` into `Span`s, styling the characters at `positions` (byte
+/// offsets) with an underline so fuzzy hits are visible, the way editors
+/// highlight fuzzy-find matches.
+fn highlight_spans(word: &str, positions: &[usize], base: Style) -> Vec<Span<'static>> {
+    let highlight = base.add_modifier(Modifier::UNDERLINED).fg(Color::Yellow);
+    word.char_indices()
+        .map(|(byte_idx, ch)| {
+            let style = if positions.contains(&byte_idx) { highlight } else { base };
+            Span::styled(ch.to_string(), style)
+        })
+        .collect()
+}
+
 fn draw_ui(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    query: &str,
-    filtered_matches: &[String],
+    query: &QueryState,
+    mode: MatchMode,
+    filtered_matches: &[Match],
     selected_index: usize,
+    marked: &HashMap<usize, Match>,
     list_state: &mut tui::widgets::ListState,
 ) -> Result<(), Box<dyn std::error::Error>> {
     terminal.draw(|f| {
@@ -65,31 +616,49 @@ fn draw_ui(
             .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
             .split(f.size());
 
-        // Query block
-        let query_block = Paragraph::new(Spans::from(vec![
-            Span::raw("Query: "),
-            Span::styled(query, Style::default().add_modifier(Modifier::BOLD)),
-        ]))
-        .block(Block::default().borders(Borders::ALL));
+        // Query block: text is split around the cursor so it can be rendered
+        // as a reversed cell, the way line-editors draw a caret.
+        let text_style = Style::default().add_modifier(Modifier::BOLD);
+        let cursor_style = text_style.add_modifier(Modifier::REVERSED);
+        let graphemes = query.graphemes();
+        let pre_cursor: String = graphemes[..query.cursor].concat();
+        let mut query_spans = vec![
+            Span::raw("Query "),
+            Span::styled(format!("[{}]", mode.label()), Style::default().fg(Color::Cyan)),
+            Span::raw(": "),
+            Span::styled(pre_cursor, text_style),
+        ];
+        if query.cursor < graphemes.len() {
+            let cursor_grapheme = graphemes[query.cursor];
+            let post_cursor: String = graphemes[query.cursor + 1..].concat();
+            query_spans.push(Span::styled(cursor_grapheme.to_string(), cursor_style));
+            query_spans.push(Span::styled(post_cursor, text_style));
+        } else {
+            // No character under the cursor: pad with a display-width-1 cell
+            // so the caret stays visually aligned after wide CJK characters.
+            let placeholder = " ".repeat(UnicodeWidthStr::width(" "));
+            query_spans.push(Span::styled(placeholder, cursor_style));
+        }
+        let query_block = Paragraph::new(Spans::from(query_spans))
+            .block(Block::default().borders(Borders::ALL));
         f.render_widget(query_block, chunks[0]);
 
-        // Matches block
+        // Matches block: the first column shows the cursor (">"), the second
+        // shows whether the entry is marked ("*") for multi-select.
         let items: Vec<ListItem> = filtered_matches
             .iter()
             .enumerate()
-            .map(|(i, item)| {
-                let content = if i == selected_index {
-                    Spans::from(vec![
-                        Span::styled(">", Style::default().add_modifier(Modifier::BOLD)),
-                        Span::styled(format!(" {}", item), Style::default().add_modifier(Modifier::BOLD)),
-                    ])
+            .map(|(i, m)| {
+                let base = if i == selected_index {
+                    Style::default().add_modifier(Modifier::BOLD)
                 } else {
-                    Spans::from(vec![
-                        Span::raw("  "), // Add padding for alignment
-                        Span::raw(item),
-                    ])
+                    Style::default()
                 };
-                ListItem::new(content)
+                let cursor_char = if i == selected_index { '>' } else { ' ' };
+                let mark_char = if marked.contains_key(&m.dict_index) { '*' } else { ' ' };
+                let mut spans = vec![Span::styled(format!("{cursor_char}{mark_char} "), base)];
+                spans.extend(highlight_spans(&m.word, &m.positions, base));
+                ListItem::new(Spans::from(spans))
             })
             .collect();
 
@@ -100,23 +669,66 @@ fn draw_ui(
     Ok(())
 }
 
+/// What happened to the picker this tick: keep going, the user aborted
+/// (Esc/Ctrl-C/Ctrl-D/Ctrl-Z), or the user confirmed a selection (Enter).
+enum InputEvent {
+    Continue,
+    Cancelled,
+    Confirmed,
+}
+
 fn handle_input(
-    query: &mut String,
+    query: &mut QueryState,
+    mode: &mut MatchMode,
     selected_index: &mut usize,
-    filtered_matches: &[String],
+    filtered_matches: &[Match],
+    marked: &mut HashMap<usize, Match>,
     running: &Arc<AtomicBool>,
-) -> Result<Option<String>, Box<dyn std::error::Error>> {
-    if event::poll(std::time::Duration::from_millis(100))? {
+) -> Result<(InputEvent, bool), Box<dyn std::error::Error>> {
+    use crossterm::event::KeyModifiers;
+
+    if !event::poll(INPUT_POLL_TIMEOUT)? {
+        return Ok((InputEvent::Continue, false));
+    }
+
+    {
         match event::read()? {
             Event::Key(KeyEvent { code, modifiers, .. }) => match code {
                 KeyCode::Esc => {
                     running.store(false, Ordering::Relaxed);
-                    return Ok(None);
+                    return Ok((InputEvent::Cancelled, true));
                 }
                 KeyCode::Char('c') | KeyCode::Char('d') | KeyCode::Char('z')
-                    if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                    if modifiers.contains(KeyModifiers::CONTROL) => {
                     running.store(false, Ordering::Relaxed);
-                    return Ok(None);
+                    return Ok((InputEvent::Cancelled, true));
+                }
+                KeyCode::Char('t') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    *mode = mode.next();
+                    save_match_mode(*mode);
+                    *selected_index = 0;
+                }
+                KeyCode::Char('a') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    query.move_home();
+                }
+                KeyCode::Char('e') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    query.move_end();
+                }
+                KeyCode::Char('w') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    query.delete_word_before_cursor();
+                    *selected_index = (*selected_index).min(filtered_matches.len().saturating_sub(1));
+                }
+                KeyCode::Left => {
+                    query.move_left();
+                }
+                KeyCode::Right => {
+                    query.move_right();
+                }
+                KeyCode::Home => {
+                    query.move_home();
+                }
+                KeyCode::End => {
+                    query.move_end();
                 }
                 KeyCode::Up => {
                     if *selected_index > 0 {
@@ -128,70 +740,179 @@ fn handle_input(
                         *selected_index += 1;
                     }
                 }
-                KeyCode::Char('p') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                KeyCode::Char('p') if modifiers.contains(KeyModifiers::CONTROL) => {
                     if *selected_index > 0 {
                         *selected_index -= 1;
                     }
                 }
-                KeyCode::Char('n') if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                KeyCode::Char('n') if modifiers.contains(KeyModifiers::CONTROL) => {
                     if *selected_index < filtered_matches.len().saturating_sub(1) {
                         *selected_index += 1;
                     }
                 }
+                KeyCode::Tab if !filtered_matches.is_empty() => {
+                    let current = &filtered_matches[*selected_index];
+                    if marked.remove(&current.dict_index).is_none() {
+                        marked.insert(current.dict_index, current.clone());
+                    }
+                }
                 KeyCode::Enter => {
                     if !filtered_matches.is_empty() {
-                        return Ok(Some(filtered_matches[*selected_index].clone()));
+                        return Ok((InputEvent::Confirmed, true));
                     }
                 }
                 KeyCode::Char(c) => {
-                    query.push(c);
+                    query.insert_char(c);
+                    *selected_index = (*selected_index).min(filtered_matches.len().saturating_sub(1));
+                }
+                KeyCode::Backspace if modifiers.contains(KeyModifiers::ALT) => {
+                    query.delete_word_before_cursor();
                     *selected_index = (*selected_index).min(filtered_matches.len().saturating_sub(1));
                 }
                 KeyCode::Backspace => {
-                    query.pop();
+                    query.delete_before_cursor();
                     *selected_index = (*selected_index).min(filtered_matches.len().saturating_sub(1));
                 }
                 _ => {}
             },
-            Event::Resize(_, _) => {} // ignore resize
+            Event::Resize(_, _) => {} // picked up by the next redraw
             _ => {} // handle other events
         }
     }
-    Ok(None)
+    Ok((InputEvent::Continue, true))
+}
+
+/// What to print alongside the selected word(s), set via `--index`/`--score`.
+#[derive(Clone, Copy)]
+enum OutputField {
+    None,
+    DictIndex,
+    Score,
+}
+
+fn format_selection(m: &Match, output_field: OutputField) -> String {
+    match output_field {
+        OutputField::None => m.word.clone(),
+        OutputField::DictIndex => format!("{}\t{}", m.dict_index, m.word),
+        OutputField::Score => format!("{}\t{}", m.score, m.word),
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let dict = load_dictionary();
+    let mut paths = Vec::new();
+    let mut output_field = OutputField::None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--index" => output_field = OutputField::DictIndex,
+            "--score" => output_field = OutputField::Score,
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    // Matched explicitly (instead of `?`) so the user sees DictError's
+    // Display message; `main`'s Termination impl would otherwise report
+    // the Box<dyn Error> via Debug.
+    let dict = match load_dictionary(&paths) {
+        Ok(dict) => Arc::new(dict),
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
     let running = setup_signal_handling();
     let mut terminal = setup_terminal()?;
 
-    let mut query = String::new();
+    let (request_tx, request_rx) = mpsc::channel::<WorkerRequest>();
+    let (result_tx, result_rx) = mpsc::channel::<WorkerResult>();
+    let latest_generation = Arc::new(AtomicU64::new(0));
+    {
+        let dict = Arc::clone(&dict);
+        let latest_generation = Arc::clone(&latest_generation);
+        thread::spawn(move || run_worker(dict, request_rx, result_tx, latest_generation));
+    }
+
+    let mut query = QueryState::new();
+    let mut mode = load_match_mode();
     let mut selected_index = 0;
+    let mut marked: HashMap<usize, Match> = HashMap::new();
     let mut list_state = tui::widgets::ListState::default();
+    let mut filtered_matches: Vec<Match> = Vec::new();
+
+    // Kick off an initial scan so the full (capped) dictionary shows up
+    // before the user types anything.
+    let mut generation: u64 = 1;
+    latest_generation.store(generation, Ordering::Relaxed);
+    request_tx.send(WorkerRequest { generation, query: query.as_str().to_string(), mode })?;
+    let mut dirty = false;
+    let mut last_edit = Instant::now();
+    // Repaint only when a fresh result lands or an input event actually
+    // fires, not on every ~30ms poll timeout; starts true to draw the
+    // initial (pre-keystroke) screen.
+    let mut needs_redraw = true;
 
-    loop {
+    let outcome = loop {
         if !running.load(Ordering::Relaxed) {
-            break;
+            break InputEvent::Cancelled;
         }
 
-        let mut matches = dict.iter()
-            .map(|w| (w, levenshtein(&query, w)))
-            .collect::<Vec<_>>();
-        matches.sort_by_key(|(_, d)| *d);
+        // Pick up the freshest result for the query/mode we actually want;
+        // stale results left over from a superseded scan are dropped. Marks
+        // are keyed by dict_index (not position), so they survive a requery.
+        while let Ok(result) = result_rx.try_recv() {
+            if result.generation == generation {
+                filtered_matches = result.matches;
+                selected_index = selected_index.min(filtered_matches.len().saturating_sub(1));
+                needs_redraw = true;
+            }
+        }
 
-        let filtered_matches: Vec<String> = matches.iter()
-            .map(|(w, _)| (*w).clone())
-            .collect();
+        if needs_redraw {
+            draw_ui(&mut terminal, &query, mode, &filtered_matches, selected_index, &marked, &mut list_state)?;
+            needs_redraw = false;
+        }
 
-        draw_ui(&mut terminal, &query, &filtered_matches, selected_index, &mut list_state)?;
-        if let Some(selected_word) = handle_input(&mut query, &mut selected_index, &filtered_matches, &running)? {
-            cleanup_terminal(&mut terminal)?;
-            println!("{}", selected_word);
-            io::stdout().flush().unwrap();
-            return Ok(());
+        let query_before = query.as_str().to_string();
+        let mode_before = mode;
+        let (event, had_event) = handle_input(&mut query, &mut mode, &mut selected_index, &filtered_matches, &mut marked, &running)?;
+        if had_event {
+            needs_redraw = true;
         }
-    }
+        if matches!(event, InputEvent::Cancelled | InputEvent::Confirmed) {
+            break event;
+        }
+        if query.as_str() != query_before || mode != mode_before {
+            dirty = true;
+            last_edit = Instant::now();
+        }
+
+        if dirty && last_edit.elapsed() >= DEBOUNCE {
+            generation += 1;
+            latest_generation.store(generation, Ordering::Relaxed);
+            request_tx.send(WorkerRequest { generation, query: query.as_str().to_string(), mode })?;
+            dirty = false;
+        }
+    };
 
     cleanup_terminal(&mut terminal)?;
-    Ok(())
-}
\ No newline at end of file
+
+    match outcome {
+        InputEvent::Confirmed => {
+            let mut selected: Vec<Match> = if marked.is_empty() {
+                filtered_matches.get(selected_index).cloned().into_iter().collect()
+            } else {
+                marked.into_values().collect()
+            };
+            selected.sort_by_key(|m| m.dict_index);
+            let output = selected
+                .iter()
+                .map(|m| format_selection(m, output_field))
+                .collect::<Vec<_>>()
+                .join("\n");
+            println!("{output}");
+            io::stdout().flush().unwrap();
+            Ok(())
+        }
+        // Cancelling prints nothing to stdout and exits nonzero so scripts
+        // can tell "user aborted" apart from "user picked nothing".
+        _ => std::process::exit(1),
+    }
+}